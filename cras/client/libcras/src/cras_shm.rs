@@ -7,16 +7,19 @@ use std::os::unix::io::{AsRawFd, RawFd};
 use std::ptr;
 use std::ptr::NonNull;
 use std::slice;
+use std::time::Duration;
 
 use libc;
 
 use cras_sys::gen::*;
 use data_model::VolatileRef;
 
+/// The server's timestamp of when it last handled a buffer, mirroring the C `cras_timespec`.
 #[repr(C, packed)]
-struct cras_timespec {
-    tv_sec: i64,
-    tv_nsec: i64,
+#[derive(Clone, Copy)]
+pub struct cras_timespec {
+    pub tv_sec: i64,
+    pub tv_nsec: i64,
 }
 
 #[repr(C, packed)]
@@ -79,6 +82,12 @@ pub struct CrasAudioHeader<'a> {
     write_buf_idx: VolatileRef<'a, u32>,
     read_offset: [VolatileRef<'a, u32>; CRAS_NUM_SHM_BUFFERS],
     write_offset: [VolatileRef<'a, u32>; CRAS_NUM_SHM_BUFFERS],
+    write_in_progress: [VolatileRef<'a, i32>; CRAS_NUM_SHM_BUFFERS],
+    volume_scaler: VolatileRef<'a, f32>,
+    mute: VolatileRef<'a, i32>,
+    num_overruns: VolatileRef<'a, u32>,
+    ts_sec: VolatileRef<'a, i64>,
+    ts_nsec: VolatileRef<'a, i64>,
 }
 
 // It is safe to send audio buffers between threads as this struct has exclusive ownership of the
@@ -142,14 +151,39 @@ impl<'a> CrasAudioHeader<'a> {
                 vref_from_addr!(addr, write_offset[0]),
                 vref_from_addr!(addr, write_offset[1]),
             ],
+            write_in_progress: [
+                vref_from_addr!(addr, write_in_progress[0]),
+                vref_from_addr!(addr, write_in_progress[1]),
+            ],
+            volume_scaler: vref_from_addr!(addr, volume_scaler),
+            mute: vref_from_addr!(addr, mute),
+            num_overruns: vref_from_addr!(addr, num_overruns),
+            ts_sec: vref_from_addr!(addr, ts.tv_sec),
+            ts_nsec: vref_from_addr!(addr, ts.tv_nsec),
         })
     }
 
-    /// Gets the write offset of the buffer and the writable length.
-    pub fn get_offset_and_len(&self) -> (usize, usize) {
+    /// Gets the write offset of the buffer and the writable length. Marks the returned
+    /// buffer's `write_in_progress` flag so the reader can tell a write is in flight.
+    pub fn get_offset_and_len(&mut self) -> (usize, usize) {
+        let used_size = self.get_used_size();
+        let idx = self.get_write_buf_idx() as usize;
+        self.write_in_progress[idx].store(1);
+        (idx * used_size, used_size)
+    }
+
+    /// Gets the read offset of the buffer and the number of bytes captured but not yet
+    /// consumed by `commit_read_frames`, so a caller never reads more than
+    /// `commit_read_frames` will accept.
+    pub fn get_readable_offset_and_len(&self) -> (usize, usize) {
+        let idx = self.get_read_buf_idx() as usize;
         let used_size = self.get_used_size();
-        let offset = self.get_write_buf_idx() as usize * used_size;
-        (offset, used_size)
+        let read_offset = self.get_read_offset(idx).unwrap_or(0) as usize;
+        let write_offset = self.get_write_offset(idx).unwrap_or(0) as usize;
+        (
+            idx * used_size + read_offset,
+            write_offset.saturating_sub(read_offset),
+        )
     }
 
     /// Gets the number of bytes per frame from the shared memory structure.
@@ -166,6 +200,73 @@ impl<'a> CrasAudioHeader<'a> {
         self.used_size.load() as usize
     }
 
+    /// Gets the software volume scaling factor, in the range `0.0..=1.0`.
+    pub fn get_volume_scaler(&self) -> f32 {
+        self.volume_scaler.load()
+    }
+
+    /// Sets the software volume scaling factor, clamping `volume_scaler` into `0.0..=1.0`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `volume_scaler` is `NaN`.
+    pub fn set_volume_scaler(&mut self, volume_scaler: f32) -> io::Result<()> {
+        if volume_scaler.is_nan() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "volume_scaler is NaN.",
+            ));
+        }
+        self.volume_scaler.store(volume_scaler.clamp(0.0, 1.0));
+        Ok(())
+    }
+
+    /// Gets whether the stream is muted.
+    pub fn get_mute(&self) -> bool {
+        self.mute.load() != 0
+    }
+
+    /// Sets whether the stream is muted.
+    pub fn set_mute(&mut self, mute: bool) {
+        self.mute.store(mute as i32);
+    }
+
+    /// Gets the number of times the server has overwritten unread data in this stream's buffer.
+    pub fn get_num_overruns(&self) -> u32 {
+        self.num_overruns.load()
+    }
+
+    /// Checks whether `num_overruns` has advanced since `last` was observed.
+    ///
+    /// # Returns
+    ///
+    /// `Some(delta)` with the number of new overruns since `last`, or `None` if the counter
+    /// hasn't advanced.
+    pub fn check_overrun_since(&self, last: u32) -> Option<u32> {
+        let current = self.get_num_overruns();
+        if current == last {
+            None
+        } else {
+            Some(current.wrapping_sub(last))
+        }
+    }
+
+    /// Gets the server's timestamp of when it last handled this stream's buffer.
+    pub fn get_timestamp(&self) -> Duration {
+        Duration::new(
+            self.ts_sec.load().max(0) as u64,
+            self.ts_nsec.load().max(0) as u32,
+        )
+    }
+
+    /// Computes the latency between the server's last buffer timestamp and `now`, a
+    /// caller-supplied monotonic time. Clamps to `Duration::default()` if `now` is
+    /// earlier than the stored timestamp.
+    pub fn latency_since(&self, now: cras_timespec) -> Duration {
+        let now = Duration::new(now.tv_sec.max(0) as u64, now.tv_nsec.max(0) as u32);
+        now.checked_sub(self.get_timestamp()).unwrap_or_default()
+    }
+
     /// Gets the index of the current written buffer.
     ///
     /// # Returns
@@ -174,12 +275,26 @@ impl<'a> CrasAudioHeader<'a> {
         self.write_buf_idx.load() & CRAS_NUM_SHM_BUFFERS_MASK
     }
 
+    /// Gets the index of the current read buffer.
+    ///
+    /// # Returns
+    /// `u32` - the returned index is less than `CRAS_NUM_SHM_BUFFERS`.
+    fn get_read_buf_idx(&self) -> u32 {
+        self.read_buf_idx.load() & CRAS_NUM_SHM_BUFFERS_MASK
+    }
+
     /// Switches the written buffer.
     fn switch_write_buf_idx(&mut self) {
         self.write_buf_idx
             .store(self.get_write_buf_idx() as u32 ^ 1u32)
     }
 
+    /// Switches the read buffer.
+    fn switch_read_buf_idx(&mut self) {
+        self.read_buf_idx
+            .store(self.get_read_buf_idx() as u32 ^ 1u32)
+    }
+
     /// Checks if the offset value for setting write_offset or read_offset is
     /// out of range or not.
     ///
@@ -237,10 +352,19 @@ impl<'a> CrasAudioHeader<'a> {
         Ok(())
     }
 
+    /// Checks whether a write is in flight on buffer `idx`.
+    ///
+    /// # Arguments
+    /// `idx` - 0 <= `idx` < `CRAS_NUM_SHM_BUFFERS`
+    pub fn is_write_in_progress(&self, idx: usize) -> bool {
+        self.write_in_progress[idx].load() != 0
+    }
+
     /// Commits written frames by switching the current buffer to the other one
     /// after samples are ready and indexes of current buffer are all set.
     /// - Sets `write_offset` of current buffer to `frame_count * frame_size`
     /// - Sets `read_offset` of current buffer to `0`.
+    /// - Clears `write_in_progress` of current buffer.
     ///
     /// # Arguments
     ///
@@ -251,8 +375,8 @@ impl<'a> CrasAudioHeader<'a> {
     /// * Returns error if `frame_count` is larger than buffer size
     ///
     /// This function is safe because we switch `write_buf_idx` after letting
-    /// `write_offset` and `read_offset` ready and we read / write shared memory
-    /// variables with volatile operations.
+    /// `write_offset` and `read_offset` ready and clearing `write_in_progress`, and we read /
+    /// write shared memory variables with volatile operations.
     pub fn commit_written_frames(&mut self, frame_count: u32) -> io::Result<()> {
         // Uses `u64` to prevent possible overflow
         let byte_count = frame_count as u64 * self.get_frame_size() as u64;
@@ -267,11 +391,64 @@ impl<'a> CrasAudioHeader<'a> {
             self.set_write_offset(idx, byte_count as u32)?;
             // Sets `read_offset` of current buffer to `0`.
             self.set_read_offset(idx, 0)?;
+            // Clears `write_in_progress` of current buffer before switching so the
+            // reader never observes a half-written buffer.
+            self.write_in_progress[idx].store(0);
             // Switch to the other buffer
             self.switch_write_buf_idx();
             Ok(())
         }
     }
+
+    /// Gets `write_offset[idx]`, the count of bytes of valid captured audio in buffer `idx`.
+    fn get_write_offset(&self, idx: usize) -> io::Result<u32> {
+        self.write_offset
+            .get(idx)
+            .map(|offset| offset.load())
+            .ok_or_else(index_out_of_range)
+    }
+
+    /// Gets `read_offset[idx]`, the count of bytes already consumed from buffer `idx`.
+    fn get_read_offset(&self, idx: usize) -> io::Result<u32> {
+        self.read_offset
+            .get(idx)
+            .map(|offset| offset.load())
+            .ok_or_else(index_out_of_range)
+    }
+
+    /// Commits read frames by adding `frame_count * frame_size` to `read_offset` of the
+    /// current read buffer and, once every byte of `write_offset` has been consumed,
+    /// switching `read_buf_idx` to the other buffer. A capture reader may call this several
+    /// times with partial reads before the buffer is released.
+    ///
+    /// # Arguments
+    ///
+    /// * `frame_count` - Number of frames read from the current buffer
+    ///
+    /// # Errors
+    ///
+    /// * Returns error if `read_offset + frame_count * frame_size` is larger than
+    ///   `write_offset`, i.e. more bytes than were captured.
+    pub fn commit_read_frames(&mut self, frame_count: u32) -> io::Result<()> {
+        // Uses `u64` to prevent possible overflow
+        let byte_count = frame_count as u64 * self.get_frame_size() as u64;
+        let idx = self.get_read_buf_idx() as usize;
+        let written = self.get_write_offset(idx)? as u64;
+        let read = self.get_read_offset(idx)? as u64 + byte_count;
+        if read > written {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "frame_count * frame_size is larger than the buffer's unread bytes",
+            ));
+        }
+        // Accumulates the bytes consumed from the current buffer so far.
+        self.set_read_offset(idx, read as u32)?;
+        // Switch to the other buffer once every written byte has been consumed.
+        if read == written {
+            self.switch_read_buf_idx();
+        }
+        Ok(())
+    }
 }
 
 impl<'a> Drop for CrasAudioHeader<'a> {
@@ -562,6 +739,124 @@ mod tests {
         assert_eq!(header.write_buf_idx.load(), 1);
     }
 
+    #[test]
+    fn cras_audio_header_commit_read_frame_test() {
+        let mut header = create_cras_audio_header("/tmp_cras_audio_header5", 20);
+        header.frame_size.store(2);
+        header.used_size.store(10);
+        header.write_offset[0].store(10);
+        assert!(header.commit_read_frames(5).is_ok());
+        assert_eq!(header.read_offset[0].load(), 10);
+        assert_eq!(header.read_buf_idx.load(), 1);
+    }
+
+    #[test]
+    fn cras_audio_header_commit_read_frame_partial_read_test() {
+        let mut header = create_cras_audio_header("/tmp_cras_audio_header5_partial", 20);
+        header.frame_size.store(2);
+        header.used_size.store(10);
+        // Only 8 of the 10 usable bytes were captured this cycle.
+        header.write_offset[0].store(8);
+
+        // First partial read: 2 frames (4 bytes) of 8 captured bytes. Buffer isn't released.
+        assert!(header.commit_read_frames(2).is_ok());
+        assert_eq!(header.read_offset[0].load(), 4);
+        assert_eq!(header.read_buf_idx.load(), 0);
+
+        // Second partial read drains the rest of the captured bytes, so the buffer is released.
+        assert!(header.commit_read_frames(2).is_ok());
+        assert_eq!(header.read_offset[0].load(), 8);
+        assert_eq!(header.read_buf_idx.load(), 1);
+
+        // Reading past what was captured is rejected.
+        assert!(header.commit_read_frames(1).is_err());
+    }
+
+    #[test]
+    fn cras_audio_header_get_readable_offset_and_len_test() {
+        let mut header = create_cras_audio_header("/tmp_cras_audio_header5_readable", 20);
+        header.frame_size.store(2);
+        header.used_size.store(10);
+        // Only 8 of the 10 usable bytes were captured this cycle.
+        header.write_offset[0].store(8);
+
+        // Nothing has been read yet: the readable length matches what was captured, not
+        // `used_size`.
+        assert_eq!(header.get_readable_offset_and_len(), (0, 8));
+
+        // After a partial read, the offset advances past what was consumed and the readable
+        // length shrinks to match.
+        assert!(header.commit_read_frames(2).is_ok());
+        assert_eq!(header.get_readable_offset_and_len(), (4, 4));
+    }
+
+    #[test]
+    fn cras_audio_header_volume_scaler_and_mute_test() {
+        let mut header = create_cras_audio_header("/tmp_cras_audio_header6", 20);
+        assert!(header.set_volume_scaler(0.5).is_ok());
+        assert_eq!(header.get_volume_scaler(), 0.5);
+        // Out-of-range values are clamped rather than rejected.
+        assert!(header.set_volume_scaler(-0.1).is_ok());
+        assert_eq!(header.get_volume_scaler(), 0.0);
+        assert!(header.set_volume_scaler(1.1).is_ok());
+        assert_eq!(header.get_volume_scaler(), 1.0);
+        assert!(header.set_volume_scaler(f32::NAN).is_err());
+
+        assert!(!header.get_mute());
+        header.set_mute(true);
+        assert!(header.get_mute());
+        header.set_mute(false);
+        assert!(!header.get_mute());
+    }
+
+    #[test]
+    fn cras_audio_header_check_overrun_since_test() {
+        let header = create_cras_audio_header("/tmp_cras_audio_header7", 20);
+        assert_eq!(header.get_num_overruns(), 0);
+        assert_eq!(header.check_overrun_since(0), None);
+        header.num_overruns.store(3);
+        assert_eq!(header.check_overrun_since(0), Some(3));
+        assert_eq!(header.check_overrun_since(3), None);
+    }
+
+    #[test]
+    fn cras_audio_header_latency_since_test() {
+        let header = create_cras_audio_header("/tmp_cras_audio_header8", 20);
+        header.ts_sec.store(10);
+        header.ts_nsec.store(0);
+        assert_eq!(header.get_timestamp(), Duration::new(10, 0));
+
+        let now = cras_timespec {
+            tv_sec: 12,
+            tv_nsec: 0,
+        };
+        assert_eq!(header.latency_since(now), Duration::new(2, 0));
+
+        // `now` earlier than the stored timestamp clamps to zero.
+        let earlier = cras_timespec {
+            tv_sec: 5,
+            tv_nsec: 0,
+        };
+        assert_eq!(header.latency_since(earlier), Duration::new(0, 0));
+    }
+
+    #[test]
+    fn cras_audio_header_write_in_progress_test() {
+        let mut header = create_cras_audio_header("/tmp_cras_audio_header9", 20);
+        header.frame_size.store(2);
+        header.used_size.store(10);
+        header.read_offset[0].store(10);
+
+        assert!(!header.is_write_in_progress(0));
+        header.get_offset_and_len();
+        assert!(header.is_write_in_progress(0));
+
+        assert!(header.commit_written_frames(5).is_ok());
+        // The flag on the buffer just committed must be cleared before the index switch.
+        assert!(!header.is_write_in_progress(0));
+        assert_eq!(header.write_buf_idx.load(), 1);
+    }
+
     #[test]
     fn create_header_and_buffers_test() {
         let samples_offset = CrasAudioShmArea::offset_of_samples() as usize;
@@ -0,0 +1,91 @@
+// Copyright 2020 The ChromiumOS Authors
+// Use of this source code is governed by a BSD-style license that can be
+// found in the LICENSE file.
+//! `amp` calibrates and queries speaker amps on behalf of `sound_card_init`, through either the
+//! real `cras` hardware backend or a `null` dry-run backend selected by `AmpBuilder`.
+mod config;
+mod cras_amp;
+mod null_amp;
+
+use std::error;
+use std::fmt;
+
+pub use cras_amp::CrasAmp;
+pub use null_amp::NullAmp;
+
+/// Result type used throughout the `amp` crate.
+pub type Result<T> = std::result::Result<T, Error>;
+
+#[derive(Debug)]
+/// Errors generated by the `amp` crate.
+pub enum Error {
+    /// The `--amp` spec's config file could not be read or parsed, with a description.
+    Config(String),
+    /// A channel index passed to an `Amp` method is out of range of the parsed config.
+    InvalidChannel(usize),
+    /// The operation is not yet implemented for this backend, with a description.
+    NotImplemented(String),
+    /// `AmpBuilder::build` was given a `backend` it does not recognize.
+    UnknownBackend(String),
+}
+
+impl error::Error for Error {}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::Config(e) => write!(f, "failed to read amp config: {}", e),
+            Error::InvalidChannel(channel) => write!(f, "invalid channel index: {}", channel),
+            Error::NotImplemented(what) => write!(f, "not implemented: {}", what),
+            Error::UnknownBackend(backend) => write!(f, "unknown amp backend: {}", backend),
+        }
+    }
+}
+
+/// A speaker amp that can be calibrated and queried, through either real hardware or a dry-run
+/// backend. Built from an `--amp` spec by `AmpBuilder`.
+pub trait Amp {
+    /// Returns the currently applied rdc of `channel`, in ohms.
+    fn get_applied_rdc(&mut self, channel: usize) -> Result<f32>;
+    /// Runs the boot time calibration flow for every channel.
+    fn boot_time_calibration(&mut self) -> Result<()>;
+    /// Returns the number of channels in the parsed config.
+    fn num_channels(&self) -> usize;
+    /// Returns `channel`'s datasheet rdc bounds as `(lower_bound_ohm, upper_bound_ohm)`.
+    fn get_rdc_bounds(&self, channel: usize) -> Result<(f32, f32)>;
+    /// Returns `channel`'s most recently measured temperature, in Celsius.
+    fn get_measured_temperature(&mut self, channel: usize) -> Result<f32>;
+    /// Returns why calibration was skipped for `channel`, or `None` if it was applied.
+    fn calibration_skip_reason(&self, channel: usize) -> Option<String>;
+}
+
+/// Builds an `Amp` from an `--amp` spec's fields, selecting the implementation named by
+/// `backend`.
+pub struct AmpBuilder<'a> {
+    sound_card_id: &'a str,
+    amp: &'a str,
+    conf: &'a str,
+    backend: &'a str,
+}
+
+impl<'a> AmpBuilder<'a> {
+    /// Creates an `AmpBuilder` for `sound_card_id`/`amp`, configured by `conf` and calibrated
+    /// through `backend` (`"cras"` for real hardware, `"null"` for the no-op dry-run amp).
+    pub fn new(sound_card_id: &'a str, amp: &'a str, conf: &'a str, backend: &'a str) -> Self {
+        Self {
+            sound_card_id,
+            amp,
+            conf,
+            backend,
+        }
+    }
+
+    /// Builds the `Amp` selected by `backend`.
+    pub fn build(self) -> Result<Box<dyn Amp>> {
+        match self.backend {
+            "cras" => Ok(Box::new(CrasAmp::new(self.sound_card_id, self.amp, self.conf)?)),
+            "null" => Ok(Box::new(NullAmp::new(self.sound_card_id, self.amp, self.conf)?)),
+            backend => Err(Error::UnknownBackend(backend.to_owned())),
+        }
+    }
+}
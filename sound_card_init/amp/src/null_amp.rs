@@ -0,0 +1,155 @@
+// Copyright 2020 The ChromiumOS Authors
+// Use of this source code is governed by a BSD-style license that can be
+// found in the LICENSE file.
+//! A no-op `Amp` backend for validating configs and exercising `sound_card_init` on machines
+//! without the physical codec present.
+use libchromeos::sys::info;
+
+use crate::config::Config;
+use crate::Amp;
+use crate::Result;
+
+/// Synthetic RDC value returned for every channel, in ohms.
+const SYNTHETIC_RDC_IN_OHM: f32 = 0.0;
+/// Synthetic temperature value used for every calibration step, in Celsius.
+const SYNTHETIC_TEMPERATURE_IN_CELSIUS: f32 = 25.0;
+
+/// A dry-run `Amp` that parses the YAML config and logs every calibration step it would
+/// perform (temperature read, rdc measurement, register writes) without touching hardware.
+pub struct NullAmp {
+    sound_card_id: String,
+    amp: String,
+    config: Config,
+}
+
+impl NullAmp {
+    /// Parses `conf` and creates a `NullAmp` for the given sound card id and speaker amp.
+    pub fn new(sound_card_id: &str, amp: &str, conf: &str) -> Result<Self> {
+        Ok(Self {
+            sound_card_id: sound_card_id.to_owned(),
+            amp: amp.to_owned(),
+            config: Config::from_file(conf)?,
+        })
+    }
+}
+
+impl Amp for NullAmp {
+    fn get_applied_rdc(&mut self, channel: usize) -> Result<f32> {
+        info!(
+            "[dry-run] {}/{}: would read applied rdc of channel {}",
+            self.sound_card_id, self.amp, channel
+        );
+        Ok(SYNTHETIC_RDC_IN_OHM)
+    }
+
+    fn boot_time_calibration(&mut self) -> Result<()> {
+        for channel in 0..self.config.channels.len() {
+            info!(
+                "[dry-run] {}/{}: channel {}: would read temperature ({}C), \
+                 measure rdc ({}ohm) and write calibrated registers",
+                self.sound_card_id,
+                self.amp,
+                channel,
+                SYNTHETIC_TEMPERATURE_IN_CELSIUS,
+                SYNTHETIC_RDC_IN_OHM
+            );
+        }
+        Ok(())
+    }
+
+    fn num_channels(&self) -> usize {
+        self.config.channels.len()
+    }
+
+    fn get_rdc_bounds(&self, channel: usize) -> Result<(f32, f32)> {
+        let channel = self.config.channel(channel)?;
+        Ok((channel.rdc_lower_bound_ohm, channel.rdc_upper_bound_ohm))
+    }
+
+    fn get_measured_temperature(&mut self, channel: usize) -> Result<f32> {
+        info!(
+            "[dry-run] {}/{}: would read measured temperature of channel {}",
+            self.sound_card_id, self.amp, channel
+        );
+        Ok(SYNTHETIC_TEMPERATURE_IN_CELSIUS)
+    }
+
+    fn calibration_skip_reason(&self, _channel: usize) -> Option<String> {
+        Some("dry-run backend: no hardware calibration performed".to_owned())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs::File;
+    use std::io::Write;
+
+    fn write_conf(path: &str, channels: usize) {
+        let mut channels_yaml = String::new();
+        for _ in 0..channels {
+            channels_yaml.push_str("  - rdc_lower_bound_ohm: 1.0\n    rdc_upper_bound_ohm: 2.0\n");
+        }
+        let mut f = File::create(path).unwrap();
+        write!(f, "channels:\n{}", channels_yaml).unwrap();
+    }
+
+    #[test]
+    fn null_amp_get_applied_rdc_is_synthetic() {
+        let path = "/tmp/null_amp_test_conf_1.yaml";
+        write_conf(path, 2);
+        let mut amp = NullAmp::new("sofcmlmax98390d", "max98390", path).unwrap();
+        assert_eq!(amp.get_applied_rdc(0).unwrap(), SYNTHETIC_RDC_IN_OHM);
+    }
+
+    #[test]
+    fn null_amp_boot_time_calibration_never_touches_hardware() {
+        let path = "/tmp/null_amp_test_conf_2.yaml";
+        write_conf(path, 2);
+        let mut amp = NullAmp::new("sofcmlmax98390d", "max98390", path).unwrap();
+        assert!(amp.boot_time_calibration().is_ok());
+    }
+
+    #[test]
+    fn null_amp_num_channels_matches_config() {
+        let path = "/tmp/null_amp_test_conf_3.yaml";
+        write_conf(path, 2);
+        let amp = NullAmp::new("sofcmlmax98390d", "max98390", path).unwrap();
+        assert_eq!(amp.num_channels(), 2);
+    }
+
+    #[test]
+    fn null_amp_get_rdc_bounds_returns_config_bounds() {
+        let path = "/tmp/null_amp_test_conf_4.yaml";
+        write_conf(path, 2);
+        let amp = NullAmp::new("sofcmlmax98390d", "max98390", path).unwrap();
+        assert_eq!(amp.get_rdc_bounds(0).unwrap(), (1.0, 2.0));
+    }
+
+    #[test]
+    fn null_amp_get_rdc_bounds_rejects_out_of_range_channel() {
+        let path = "/tmp/null_amp_test_conf_5.yaml";
+        write_conf(path, 1);
+        let amp = NullAmp::new("sofcmlmax98390d", "max98390", path).unwrap();
+        assert!(amp.get_rdc_bounds(1).is_err());
+    }
+
+    #[test]
+    fn null_amp_get_measured_temperature_is_synthetic() {
+        let path = "/tmp/null_amp_test_conf_6.yaml";
+        write_conf(path, 1);
+        let mut amp = NullAmp::new("sofcmlmax98390d", "max98390", path).unwrap();
+        assert_eq!(
+            amp.get_measured_temperature(0).unwrap(),
+            SYNTHETIC_TEMPERATURE_IN_CELSIUS
+        );
+    }
+
+    #[test]
+    fn null_amp_calibration_skip_reason_is_always_set() {
+        let path = "/tmp/null_amp_test_conf_7.yaml";
+        write_conf(path, 1);
+        let amp = NullAmp::new("sofcmlmax98390d", "max98390", path).unwrap();
+        assert!(amp.calibration_skip_reason(0).is_some());
+    }
+}
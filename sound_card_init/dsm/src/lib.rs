@@ -0,0 +1,5 @@
+// Copyright 2020 The ChromiumOS Authors
+// Use of this source code is governed by a BSD-style license that can be
+// found in the LICENSE file.
+//! `dsm` holds helpers shared by `sound_card_init`'s dynamic speaker management code.
+pub mod utils;
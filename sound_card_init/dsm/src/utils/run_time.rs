@@ -0,0 +1,35 @@
+// Copyright 2020 The ChromiumOS Authors
+// Use of this source code is governed by a BSD-style license that can be
+// found in the LICENSE file.
+//! Tracks when `sound_card_init` last ran for a given sound card, and measures elapsed time
+//! using the same clock.
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Directory the per-sound-card run time marker files are written to.
+const RUN_TIME_DIR: &str = "/var/lib/sound_card_init/run_time";
+
+/// Returns the number of seconds since the Unix epoch, as a float so elapsed durations can be
+/// computed by simple subtraction.
+pub fn now() -> io::Result<f64> {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs_f64())
+        .map_err(|e| io::Error::new(io::ErrorKind::Other, e))
+}
+
+/// Returns the path of `sound_card_id`'s run time marker file.
+fn marker_path(sound_card_id: &str) -> PathBuf {
+    PathBuf::from(RUN_TIME_DIR).join(sound_card_id)
+}
+
+/// Records the current time as the last time `sound_card_init` ran for `sound_card_id`.
+pub fn now_to_file(sound_card_id: &str) -> io::Result<()> {
+    let path = marker_path(sound_card_id);
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    fs::write(path, now()?.to_string())
+}